@@ -2,18 +2,18 @@
 
 use codec::FullCodec;
 use frame_support::{
-	decl_error, decl_event, decl_module, decl_storage,
-	traits::{Get, Currency, ExistenceRequirement, WithdrawReason},
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	storage::{with_transaction, TransactionOutcome},
+	traits::{Currency, EnsureOrigin, ExistenceRequirement, Get, WithdrawReason},
 	Parameter,
 	debug
 };
 
 use sp_runtime::{
-	traits::{CheckedConversion, Convert, SaturatedConversion, Member, AtLeast32Bit, MaybeSerializeDeserialize},
+	traits::{CheckedConversion, SaturatedConversion, Member, AtLeast32Bit, MaybeSerializeDeserialize},
 	DispatchResult, RuntimeDebug,
 };
 use sp_std::{
-	collections::btree_map::BTreeMap,
 	convert::{TryFrom, TryInto},
 	marker::PhantomData,
 	prelude::*,
@@ -23,7 +23,9 @@ use sp_std::{
 
 use codec::{Decode, Encode};
 
-use xcm::v0::{Error, Junction, MultiAsset, MultiLocation, Result};
+use orml_traits::MultiCurrency;
+
+use xcm::v0::{AssetInstance, Error as XcmError, Junction, MultiAsset, MultiLocation, NetworkId, Result};
 use xcm_executor::traits::{FilterAssetLocation, LocationConversion, MatchesFungible, NativeAsset, TransactAsset};
 use cumulus_primitives::ParaId;
 
@@ -72,10 +74,59 @@ pub trait Trait: frame_system::Trait {
 	type Matcher: MatchesFungible<Self::Balance>;
 	type AccountIdConverter: LocationConversion<Self::AccountId>;
 	type XCurrencyIdConverter: XCurrencyIdConversion;
+	/// Credits/debits the derivative balance backing a fungible reserve asset.
+	type Currency: MultiCurrency<Self::AccountId, CurrencyId = PHAXCurrencyId, Balance = Self::Balance>;
+	/// Store for assets `XCurrencyIdConverter` doesn't recognize. Set to `()` to reject them.
+	type UnknownAsset: UnknownAsset;
+	/// Hook run at the end of a fungible `deposit_asset`, after the balance is credited.
+	/// Not invoked for non-fungible deposits, nor for deposits parked in `UnknownAsset`.
+	type OnDeposit: OnXcmDeposit<Self::AccountId, PHAXCurrencyId, Self::Balance>;
+	/// Recognizes non-fungible `MultiAsset`s and resolves them to a (class, instance) pair.
+	type NonFungibleMatcher: MatchesNonFungible;
+	/// Mints/burns the derivative non-fungible items backing NFT reserve transfers.
+	type Nfts: Nfts<Self::AccountId>;
+	/// Origin allowed to register, move, and deregister assets in the asset registry.
+	type RegisterOrigin: EnsureOrigin<Self::Origin>;
 }
 
 decl_storage! {
-	trait Store for Module<T: Trait> as PhalaXCMAdapter {}
+	trait Store for Module<T: Trait> as PhalaXCMAdapter {
+		/// Recorded balances of concrete fungible assets that have no known
+		/// `PHAXCurrencyId` mapping yet, keyed by (owner location, asset location).
+		pub ConcreteFungibleBalances get(fn concrete_fungible_balances):
+			map hasher(blake2_128_concat) (MultiLocation, MultiLocation) => u128;
+
+		/// Recorded balances of abstract fungible assets that have no known
+		/// `PHAXCurrencyId` mapping yet, keyed by (owner location, abstract asset id).
+		pub AbstractFungibleBalances get(fn abstract_fungible_balances):
+			map hasher(blake2_128_concat) (MultiLocation, Vec<u8>) => u128;
+
+		/// Registry of asset keys (e.g. the raw ticker, or `b"DOT"` for the relay chain
+		/// native asset) to the `MultiLocation` they are reserved on.
+		pub AssetToLocation get(fn asset_to_location):
+			map hasher(blake2_128_concat) Vec<u8> => Option<MultiLocation>;
+
+		/// The reverse mapping from a reserve `MultiLocation` to the `PHAXCurrencyId`
+		/// registered for it.
+		pub LocationToAsset get(fn location_to_asset):
+			map hasher(blake2_128_concat) MultiLocation => Option<PHAXCurrencyId>;
+
+		/// Decimal normalization ratio (numerator, denominator) per reserve `MultiLocation`.
+		/// Applied as `amount * numerator / denominator`.
+		pub AssetRatio get(fn asset_ratio):
+			map hasher(blake2_128_concat) MultiLocation => (u128, u128);
+	}
+}
+
+decl_error! {
+	pub enum Error for Module<T: Trait> {
+		/// This asset key is already registered.
+		AssetAlreadyRegistered,
+		/// This asset key has not been registered.
+		AssetNotRegistered,
+		/// This reserve location is already registered under a different asset key.
+		LocationAlreadyRegistered,
+	}
 }
 
 decl_event! (
@@ -88,18 +139,67 @@ decl_event! (
 
 		/// Withdraw asset from current chain. [currency_id, account_id, amount, to_tee]
 		WithdrawAsset(Vec<u8>, AccountId, Balance, bool),
+
+		/// Deposit a non-fungible asset into current chain. [class, instance, account_id, to_tee]
+		DepositNonFungible(Vec<u8>, u128, AccountId, bool),
+
+		/// Withdraw a non-fungible asset from current chain. [class, instance, account_id, to_tee]
+		WithdrawNonFungible(Vec<u8>, u128, AccountId, bool),
 	}
 );
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
 
         fn deposit_event() = default;
 
+		/// Register a new cross-chain asset under `key`, reserved on `location` and
+		/// addressed locally as `currency_id`. `ratio` is the (numerator, denominator)
+		/// applied to rescale deposited/withdrawn amounts to local decimal precision.
+		#[weight = 10_000]
+		pub fn register_asset(origin, key: Vec<u8>, currency_id: PHAXCurrencyId, location: MultiLocation, ratio: (u128, u128)) {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			ensure!(!AssetToLocation::contains_key(&key), Error::<T>::AssetAlreadyRegistered);
+			ensure!(!LocationToAsset::contains_key(&location), Error::<T>::LocationAlreadyRegistered);
+
+			AssetToLocation::insert(&key, location.clone());
+			LocationToAsset::insert(location.clone(), currency_id);
+			AssetRatio::insert(location, ratio);
+		}
+
+		/// Move a registered asset's reserve location, e.g. after it migrates to a new
+		/// parachain.
+		#[weight = 10_000]
+		pub fn update_asset_location(origin, key: Vec<u8>, location: MultiLocation) {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			let old_location = AssetToLocation::get(&key).ok_or(Error::<T>::AssetNotRegistered)?;
+			ensure!(
+				location == old_location || !LocationToAsset::contains_key(&location),
+				Error::<T>::LocationAlreadyRegistered
+			);
+			let currency_id = LocationToAsset::take(&old_location).ok_or(Error::<T>::AssetNotRegistered)?;
+			let ratio = AssetRatio::take(&old_location);
+
+			AssetToLocation::insert(&key, location.clone());
+			LocationToAsset::insert(location.clone(), currency_id);
+			AssetRatio::insert(location, ratio);
+		}
+
+		/// Remove a registered asset, so future deposits/withdrawals under `key` are
+		/// parked in the unknown asset store instead of being resolved.
+		#[weight = 10_000]
+		pub fn deregister_asset(origin, key: Vec<u8>) {
+			T::RegisterOrigin::ensure_origin(origin)?;
+			let location = AssetToLocation::take(&key).ok_or(Error::<T>::AssetNotRegistered)?;
+			LocationToAsset::remove(&location);
+			AssetRatio::remove(location);
+		}
+
     }
 }
 
-impl<T> TransactAsset for Module<T> where 
+impl<T> TransactAsset for Module<T> where
     T: Trait,
 {
     fn deposit_asset(asset: &MultiAsset, location: &MultiLocation) -> Result {
@@ -108,13 +208,47 @@ impl<T> TransactAsset for Module<T> where
 
 		let who = T::AccountIdConverter::from_location(location).ok_or(())?;
 		debug::info!("who: {:?}", who);
-		let currency_id = T::XCurrencyIdConverter::from_asset_and_location(asset, location).ok_or(())?;
+
+		if let Some((class, instance)) = T::NonFungibleMatcher::matches_nonfungible(asset) {
+			debug::info!("non-fungible class: {:?}, instance: {:?}", class, instance);
+			T::Nfts::mint_into(&class, instance, &who).map_err(|_| ())?;
+
+			Self::deposit_event(
+				Event::<T>::DepositNonFungible(class, instance, who, true),
+			);
+
+			debug::info!(">>> success non-fungible deposit.");
+			debug::info!("------------------------------------------------");
+			return Ok(());
+		}
+
+		let currency_id = match T::XCurrencyIdConverter::from_asset_and_location(asset, location) {
+			Some(currency_id) => currency_id,
+			None => {
+				debug::info!(">>> no known currency mapping, parking asset in the unknown asset store.");
+				return T::UnknownAsset::deposit(asset, location);
+			}
+		};
 		debug::info!("currency_id: {:?}", currency_id);
 		let amount = T::Matcher::matches_fungible(&asset).ok_or(())?.saturated_into();
 		debug::info!("amount: {:?}", amount);
 		let balance_amount = amount.try_into().map_err(|_| ())?;
 		debug::info!("balance amount: {:?}", balance_amount);
-        
+
+		// Credit first, then run the hook, so a handler reacting to the deposit (auto-swap,
+		// staking, ...) sees the balance in place. Wrapped in a transaction so a failing hook
+		// rolls back the credit instead of leaving it stranded.
+		with_transaction(|| {
+			let credited = T::Currency::deposit(currency_id.clone(), &who, balance_amount)
+				.map_err(|_| ())
+				.and_then(|_| T::OnDeposit::on_deposit(&who, currency_id.clone(), balance_amount).map_err(|_| ()));
+
+			match credited {
+				Ok(()) => TransactionOutcome::Commit(Ok(())),
+				Err(e) => TransactionOutcome::Rollback(Err(e)),
+			}
+		})?;
+
         Self::deposit_event(
             Event::<T>::DepositAsset(currency_id.clone().into(), who, balance_amount, true),
         );
@@ -123,20 +257,43 @@ impl<T> TransactAsset for Module<T> where
 		debug::info!("------------------------------------------------");
 		Ok(())
     }
-    
-    fn withdraw_asset(asset: &MultiAsset, location: &MultiLocation) -> result::Result<MultiAsset, Error> {
+
+    fn withdraw_asset(asset: &MultiAsset, location: &MultiLocation) -> result::Result<MultiAsset, XcmError> {
 		debug::info!("------------------------------------------------");
 		debug::info!(">>> trying withdraw. asset: {:?}, location: {:?}", asset, location);
-		
+
 		let who = T::AccountIdConverter::from_location(location).ok_or(())?;
 		debug::info!("who: {:?}", who);
-		let currency_id = T::XCurrencyIdConverter::from_asset_and_location(asset, location).ok_or(())?;
+
+		if let Some((class, instance)) = T::NonFungibleMatcher::matches_nonfungible(asset) {
+			debug::info!("non-fungible class: {:?}, instance: {:?}", class, instance);
+			T::Nfts::burn_from(&class, instance, &who).map_err(|_| ())?;
+
+			Self::deposit_event(
+				Event::<T>::WithdrawNonFungible(class, instance, who, true),
+			);
+
+			debug::info!(">>> success non-fungible withdraw.");
+			debug::info!("------------------------------------------------");
+			return Ok(asset.clone());
+		}
+
+		let currency_id = match T::XCurrencyIdConverter::from_asset_and_location(asset, location) {
+			Some(currency_id) => currency_id,
+			None => {
+				debug::info!(">>> no known currency mapping, withdrawing from the unknown asset store.");
+				T::UnknownAsset::withdraw(asset, location)?;
+				return Ok(asset.clone());
+			}
+		};
 		debug::info!("currency_id: {:?}", currency_id);
 		let amount = T::Matcher::matches_fungible(&asset).ok_or(())?.saturated_into();
 		debug::info!("amount: {:?}", amount);
 		let balance_amount = amount.try_into().map_err(|_| ())?;
 		debug::info!("balance amount: {:?}", balance_amount);
 
+		T::Currency::withdraw(currency_id.clone(), &who, balance_amount).map_err(|_| ())?;
+
         Self::deposit_event(
             Event::<T>::WithdrawAsset(currency_id.clone().into(), who, balance_amount, true),
         );
@@ -147,28 +304,188 @@ impl<T> TransactAsset for Module<T> where
 	}
 }
 
-pub struct IsConcreteWithGeneralKey<CurrencyId, FromRelayChainBalance>(
-	PhantomData<(CurrencyId, FromRelayChainBalance)>,
+/// Holds assets that `XCurrencyIdConversion` could not resolve to a known
+/// `PHAXCurrencyId`, so they can later be withdrawn instead of being discarded.
+pub trait UnknownAsset {
+	fn deposit(asset: &MultiAsset, to: &MultiLocation) -> Result;
+	fn withdraw(asset: &MultiAsset, from: &MultiLocation) -> Result;
+}
+
+impl UnknownAsset for () {
+	fn deposit(_asset: &MultiAsset, _to: &MultiLocation) -> Result {
+		Err(XcmError::Undefined)
+	}
+
+	fn withdraw(_asset: &MultiAsset, _from: &MultiLocation) -> Result {
+		Err(XcmError::Undefined)
+	}
+}
+
+impl<T: Trait> UnknownAsset for Module<T> {
+	fn deposit(asset: &MultiAsset, to: &MultiLocation) -> Result {
+		match asset {
+			MultiAsset::ConcreteFungible { id, amount } => {
+				ConcreteFungibleBalances::mutate((to.clone(), id.clone()), |b| *b = b.saturating_add(*amount));
+				Ok(())
+			}
+			MultiAsset::AbstractFungible { id, amount } => {
+				AbstractFungibleBalances::mutate((to.clone(), id.clone()), |b| *b = b.saturating_add(*amount));
+				Ok(())
+			}
+			_ => Err(XcmError::Undefined),
+		}
+	}
+
+	fn withdraw(asset: &MultiAsset, from: &MultiLocation) -> Result {
+		match asset {
+			MultiAsset::ConcreteFungible { id, amount } => {
+				ConcreteFungibleBalances::try_mutate((from.clone(), id.clone()), |b| -> Result {
+					*b = b.checked_sub(*amount).ok_or(())?;
+					Ok(())
+				})
+			}
+			MultiAsset::AbstractFungible { id, amount } => {
+				AbstractFungibleBalances::try_mutate((from.clone(), id.clone()), |b| -> Result {
+					*b = b.checked_sub(*amount).ok_or(())?;
+					Ok(())
+				})
+			}
+			_ => Err(XcmError::Undefined),
+		}
+	}
+}
+
+/// Callback run after a fungible deposit is credited, letting other pallets react to
+/// incoming XCM deposits (auto-swap, staking, TEE worker registration, ...).
+pub trait OnXcmDeposit<AccountId, CurrencyId, Balance> {
+	fn on_deposit(who: &AccountId, currency_id: CurrencyId, amount: Balance) -> DispatchResult;
+}
+
+impl<AccountId, CurrencyId, Balance> OnXcmDeposit<AccountId, CurrencyId, Balance> for () {
+	fn on_deposit(_who: &AccountId, _currency_id: CurrencyId, _amount: Balance) -> DispatchResult {
+		Ok(())
+	}
+}
+
+macro_rules! impl_on_xcm_deposit_for_tuple {
+	($($t:ident),+) => {
+		impl<AccountId, CurrencyId: Clone, Balance: Copy, $($t: OnXcmDeposit<AccountId, CurrencyId, Balance>),+>
+			OnXcmDeposit<AccountId, CurrencyId, Balance> for ($($t,)+)
+		{
+			fn on_deposit(who: &AccountId, currency_id: CurrencyId, amount: Balance) -> DispatchResult {
+				$($t::on_deposit(who, currency_id.clone(), amount)?;)+
+				Ok(())
+			}
+		}
+	};
+}
+
+impl_on_xcm_deposit_for_tuple!(A);
+impl_on_xcm_deposit_for_tuple!(A, B);
+impl_on_xcm_deposit_for_tuple!(A, B, C);
+impl_on_xcm_deposit_for_tuple!(A, B, C, D);
+impl_on_xcm_deposit_for_tuple!(A, B, C, D, E);
+impl_on_xcm_deposit_for_tuple!(A, B, C, D, E, F);
+impl_on_xcm_deposit_for_tuple!(A, B, C, D, E, F, G);
+impl_on_xcm_deposit_for_tuple!(A, B, C, D, E, F, G, H);
+
+/// Recognizes a non-fungible `MultiAsset` and resolves it to a (class, instance) pair,
+/// mirroring `MatchesFungible` for the non-fungible `ConcreteNonFungible`/`AbstractNonFungible`
+/// variants.
+pub trait MatchesNonFungible {
+	fn matches_nonfungible(a: &MultiAsset) -> Option<(Vec<u8>, u128)>;
+}
+
+/// Matches both concrete and abstract non-fungible assets whose class is identified by a
+/// `GeneralKey` junction (concrete) or raw class bytes (abstract), the non-fungible
+/// analogue of `IsConcreteWithGeneralKey`. Only `AssetInstance::Index` instances are
+/// supported; the byte-array and blob variants are rejected rather than mis-routed, since
+/// this adapter has no (collection, item) space wide enough to hold them.
+pub struct IsNonFungibleWithGeneralKey;
+impl MatchesNonFungible for IsNonFungibleWithGeneralKey {
+	fn matches_nonfungible(a: &MultiAsset) -> Option<(Vec<u8>, u128)> {
+		match a {
+			MultiAsset::ConcreteNonFungible { class, instance } => {
+				let key = if let Some(Junction::GeneralKey(key)) = class.last() { key.clone() } else { return None };
+				match instance {
+					AssetInstance::Index { id } => Some((key, *id)),
+					other => {
+						debug::info!("unsupported non-fungible instance variant: {:?}", other);
+						None
+					}
+				}
+			}
+			MultiAsset::AbstractNonFungible { class, instance } => match instance {
+				AssetInstance::Index { id } => Some((class.clone(), *id)),
+				other => {
+					debug::info!("unsupported non-fungible instance variant: {:?}", other);
+					None
+				}
+			},
+			_ => None,
+		}
+	}
+}
+
+/// A uniques-like backend able to mint and burn the derivative non-fungible items that
+/// back cross-chain NFT reserve transfers. `burn_from` must verify that `who` owns the
+/// item before burning it.
+pub trait Nfts<AccountId> {
+	fn mint_into(class: &[u8], instance: u128, who: &AccountId) -> DispatchResult;
+	fn burn_from(class: &[u8], instance: u128, who: &AccountId) -> DispatchResult;
+}
+
+/// Computes `amount * numerator / denominator`, returning `None` on overflow or a zero
+/// denominator instead of truncating.
+fn normalize_decimals(amount: u128, (numerator, denominator): (u128, u128)) -> Option<u128> {
+	if denominator == 0 {
+		return None;
+	}
+	amount.checked_mul(numerator)?.checked_div(denominator)
+}
+
+#[cfg(test)]
+mod normalize_decimals_tests {
+	use super::normalize_decimals;
+
+	#[test]
+	fn scales_by_the_ratio() {
+		assert_eq!(normalize_decimals(100, (1, 10)), Some(10));
+		assert_eq!(normalize_decimals(100, (1, 1)), Some(100));
+	}
+
+	#[test]
+	fn rejects_a_zero_denominator() {
+		assert_eq!(normalize_decimals(100, (1, 0)), None);
+	}
+
+	#[test]
+	fn rejects_overflow_instead_of_truncating() {
+		assert_eq!(normalize_decimals(u128::MAX, (2, 1)), None);
+	}
+}
+
+pub struct IsConcreteWithGeneralKey<T>(
+	PhantomData<T>,
 );
-impl<CurrencyId, B, FromRelayChainBalance> MatchesFungible<B>
-	for IsConcreteWithGeneralKey<CurrencyId, FromRelayChainBalance>
+impl<T, B> MatchesFungible<B>
+	for IsConcreteWithGeneralKey<T>
 where
-	CurrencyId: TryFrom<Vec<u8>>,
+	T: Trait,
 	B: TryFrom<u128>,
-	FromRelayChainBalance: Convert<u128, u128>,
 {
 	fn matches_fungible(a: &MultiAsset) -> Option<B> {
 		if let MultiAsset::ConcreteFungible { id, amount } = a {
-			if id == &MultiLocation::X1(Junction::Parent) {
-				// Convert relay chain decimals to local chain
-				let local_amount = FromRelayChainBalance::convert(*amount);
-				return CheckedConversion::checked_from(local_amount);
-			}
-			if let Some(Junction::GeneralKey(key)) = id.last() {
-				if TryInto::<CurrencyId>::try_into(key.clone()).is_ok() {
-					return CheckedConversion::checked_from(*amount);
-				}
-			}
+			let location = if id == &MultiLocation::X1(Junction::Parent) {
+				Some(id.clone())
+			} else if let Some(Junction::GeneralKey(key)) = id.last() {
+				AssetToLocation::get(key)
+			} else {
+				None
+			}?;
+
+			let local_amount = normalize_decimals(*amount, AssetRatio::get(&location))?;
+			return CheckedConversion::checked_from(local_amount);
 		}
 		None
 	}
@@ -178,31 +495,26 @@ pub trait XCurrencyIdConversion {
 	fn from_asset_and_location(asset: &MultiAsset, location: &MultiLocation) -> Option<PHAXCurrencyId>;
 }
 
-pub struct XCurrencyIdConverter<NativeTokens>(
-	PhantomData<NativeTokens>,
+/// Resolves assets against the on-chain asset registry (`AssetToLocation`/`LocationToAsset`)
+/// populated by `register_asset`/`update_asset_location`/`deregister_asset`, instead of a
+/// compile-time map, so new parachain tokens (and the relay-chain native entry) can be
+/// onboarded without a runtime upgrade.
+pub struct XCurrencyIdConverter<T>(
+	PhantomData<T>,
 );
-impl <NativeTokens: Get<BTreeMap<Vec<u8>, MultiLocation>>>  XCurrencyIdConversion for XCurrencyIdConverter<NativeTokens>
+impl<T: Trait> XCurrencyIdConversion for XCurrencyIdConverter<T>
 {
-	fn from_asset_and_location(multi_asset: &MultiAsset, multi_location: &MultiLocation) -> Option<PHAXCurrencyId> {
+	fn from_asset_and_location(multi_asset: &MultiAsset, _multi_location: &MultiLocation) -> Option<PHAXCurrencyId> {
 		if let MultiAsset::ConcreteFungible { ref id, .. } = multi_asset {
 			if id == &MultiLocation::X1(Junction::Parent) {
-				let relaychain_currency : PHAXCurrencyId = PHAXCurrencyId {
-					chain_id: ChainId::RelayChain,
-					currency_id: b"DOT".to_vec(),
-				};
-				return Some(relaychain_currency);
+				return LocationToAsset::get(id);
 			}
 
 			if let Some(Junction::GeneralKey(key)) = id.last() {
-				if NativeTokens::get().contains_key(&key.clone()) {
-					// here we can trust the currency matchs the parachain, case NativePalletAssetOr already check this
-					if let MultiLocation::X2(Junction::Parent, Junction::Parachain {id: paraid}) = NativeTokens::get().get(&key.clone()).unwrap() {
-						let parachain_currency: PHAXCurrencyId = PHAXCurrencyId {
-							chain_id: ChainId::ParaChain((*paraid).into()),
-							currency_id: key.clone(),
-						};
-						return Some(parachain_currency);
-					}
+				if let Some(location) = AssetToLocation::get(key) {
+					// here we can trust the currency matches the parachain, case
+					// NativePalletAssetOr already checked this at filter time
+					return LocationToAsset::get(&location);
 				}
 			}
 		}
@@ -210,8 +522,8 @@ impl <NativeTokens: Get<BTreeMap<Vec<u8>, MultiLocation>>>  XCurrencyIdConversio
 	}
 }
 
-pub struct NativePalletAssetOr<NativeTokens>(PhantomData<NativeTokens>);
-impl<NativeTokens: Get<BTreeMap<Vec<u8>, MultiLocation>>> FilterAssetLocation for NativePalletAssetOr<NativeTokens> {
+pub struct NativePalletAssetOr<T>(PhantomData<T>);
+impl<T: Trait> FilterAssetLocation for NativePalletAssetOr<T> {
 	fn filter_asset_location(asset: &MultiAsset, origin: &MultiLocation) -> bool {
 		if NativeAsset::filter_asset_location(asset, origin) {
 			return true;
@@ -220,8 +532,8 @@ impl<NativeTokens: Get<BTreeMap<Vec<u8>, MultiLocation>>> FilterAssetLocation fo
 		// native asset identified by a general key
 		if let MultiAsset::ConcreteFungible { ref id, .. } = asset {
 			if let Some(Junction::GeneralKey(key)) = id.last() {
-				if NativeTokens::get().contains_key(&key.clone()) {
-					return (*origin) == *(NativeTokens::get().get(&key.clone()).unwrap());
+				if let Some(location) = AssetToLocation::get(key) {
+					return (*origin) == location;
 				}
 			}
 		}
@@ -230,8 +542,501 @@ impl<NativeTokens: Get<BTreeMap<Vec<u8>, MultiLocation>>> FilterAssetLocation fo
 	}
 }
 
+/// Resolves a relay-chain `parent + AccountId32` location into a local `AccountId`,
+/// accepting either `NetworkId::Any` or the configured `Network`.
+pub struct RelaychainAccountId32Aliases<Network, AccountId>(PhantomData<(Network, AccountId)>);
+impl<Network, AccountId> LocationConversion<AccountId> for RelaychainAccountId32Aliases<Network, AccountId>
+where
+	Network: Get<NetworkId>,
+	AccountId: From<[u8; 32]> + Into<[u8; 32]>,
+{
+	fn from_location(location: &MultiLocation) -> Option<AccountId> {
+		if let MultiLocation::X2(Junction::Parent, Junction::AccountId32 { network, id }) = location {
+			if *network == NetworkId::Any || *network == Network::get() {
+				return Some((*id).into());
+			}
+		}
+		None
+	}
+
+	fn try_into_location(who: AccountId) -> result::Result<MultiLocation, AccountId> {
+		Ok(MultiLocation::X2(
+			Junction::Parent,
+			Junction::AccountId32 { network: Network::get(), id: who.into() },
+		))
+	}
+}
+
+#[cfg(test)]
+mod relaychain_account_id32_aliases_tests {
+	use super::*;
+	use frame_support::parameter_types;
+	use sp_core::H256;
+
+	parameter_types! {
+		pub const TestNetwork: NetworkId = NetworkId::Named(vec![1]);
+	}
+
+	type Alias = RelaychainAccountId32Aliases<TestNetwork, H256>;
+
+	fn relay_location(network: NetworkId, id: [u8; 32]) -> MultiLocation {
+		MultiLocation::X2(Junction::Parent, Junction::AccountId32 { network, id })
+	}
+
+	#[test]
+	fn from_location_accepts_any_network() {
+		assert_eq!(Alias::from_location(&relay_location(NetworkId::Any, [7u8; 32])), Some(H256::from([7u8; 32])));
+	}
+
+	#[test]
+	fn from_location_accepts_the_configured_network() {
+		let network = <TestNetwork as Get<NetworkId>>::get();
+		assert_eq!(Alias::from_location(&relay_location(network, [7u8; 32])), Some(H256::from([7u8; 32])));
+	}
+
+	#[test]
+	fn from_location_rejects_a_mismatched_network() {
+		assert_eq!(Alias::from_location(&relay_location(NetworkId::Named(vec![2]), [7u8; 32])), None);
+	}
+
+	#[test]
+	fn from_location_rejects_a_non_relaychain_location() {
+		assert_eq!(Alias::from_location(&MultiLocation::X1(Junction::Parent)), None);
+	}
+
+	#[test]
+	fn try_into_location_round_trips_through_from_location() {
+		let who = H256::from([9u8; 32]);
+		let location = Alias::try_into_location(who).unwrap();
+		assert_eq!(Alias::from_location(&location), Some(who));
+	}
+}
+
 pub trait XcmHandler {
 	type Origin;
 	type Xcm;
 	fn execute(origin: Self::Origin, xcm: Self::Xcm) -> DispatchResult;
+}
+
+#[cfg(test)]
+mod mock {
+	use super::*;
+	use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+	use sp_core::H256;
+	use sp_runtime::{testing::Header, traits::{BlakeTwo256, IdentityLookup}, DispatchError, Perbill};
+	use std::cell::RefCell;
+
+	impl_outer_origin! {
+		pub enum Origin for Runtime {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Runtime;
+
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::one();
+	}
+
+	impl frame_system::Trait for Runtime {
+		type BaseCallFilter = ();
+		type Origin = Origin;
+		type Call = ();
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type DbWeight = ();
+		type BlockExecutionWeight = ();
+		type ExtrinsicBaseWeight = ();
+		type MaximumExtrinsicWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+		type PalletInfo = ();
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+	}
+
+	/// A `MultiCurrency` backend with a real, observable balance per `(who, currency_id)`,
+	/// stored in the externalities' storage overlay (via `storage::unhashed`) rather than a
+	/// plain Rust value, so that deposits made inside `with_transaction` are actually rolled
+	/// back when the transaction is, the same as a real `Currency` implementation's storage.
+	pub struct FakeCurrency;
+	impl FakeCurrency {
+		fn key(who: u64, currency_id: &PHAXCurrencyId) -> Vec<u8> {
+			let mut key = b"xcm-adapter-test/fake-currency-balance".to_vec();
+			key.extend(who.encode());
+			key.extend(currency_id.encode());
+			key
+		}
+
+		pub fn balance_of(who: u64, currency_id: &PHAXCurrencyId) -> u128 {
+			frame_support::storage::unhashed::get(&Self::key(who, currency_id)).unwrap_or(0)
+		}
+
+		fn mutate(who: u64, currency_id: PHAXCurrencyId, f: impl FnOnce(u128) -> Option<u128>) -> DispatchResult {
+			let key = Self::key(who, &currency_id);
+			let current = frame_support::storage::unhashed::get(&key).unwrap_or(0u128);
+			let updated = f(current).ok_or(DispatchError::Other("balance update overflowed or underflowed"))?;
+			frame_support::storage::unhashed::put(&key, &updated);
+			Ok(())
+		}
+	}
+	impl MultiCurrency<u64> for FakeCurrency {
+		type CurrencyId = PHAXCurrencyId;
+		type Balance = u128;
+
+		fn minimum_balance(_currency_id: Self::CurrencyId) -> Self::Balance { 0 }
+		fn total_issuance(_currency_id: Self::CurrencyId) -> Self::Balance { 0 }
+		fn total_balance(currency_id: Self::CurrencyId, who: &u64) -> Self::Balance { Self::balance_of(*who, &currency_id) }
+		fn free_balance(currency_id: Self::CurrencyId, who: &u64) -> Self::Balance { Self::balance_of(*who, &currency_id) }
+		fn ensure_can_withdraw(currency_id: Self::CurrencyId, who: &u64, amount: Self::Balance) -> DispatchResult {
+			if Self::balance_of(*who, &currency_id) >= amount { Ok(()) } else { Err(DispatchError::Other("insufficient balance")) }
+		}
+		fn transfer(currency_id: Self::CurrencyId, from: &u64, to: &u64, amount: Self::Balance) -> DispatchResult {
+			Self::withdraw(currency_id.clone(), from, amount)?;
+			Self::deposit(currency_id, to, amount)
+		}
+		fn deposit(currency_id: Self::CurrencyId, who: &u64, amount: Self::Balance) -> DispatchResult {
+			Self::mutate(*who, currency_id, |b| b.checked_add(amount))
+		}
+		fn withdraw(currency_id: Self::CurrencyId, who: &u64, amount: Self::Balance) -> DispatchResult {
+			Self::mutate(*who, currency_id, |b| b.checked_sub(amount))
+		}
+		fn can_slash(currency_id: Self::CurrencyId, who: &u64, amount: Self::Balance) -> bool { Self::balance_of(*who, &currency_id) >= amount }
+		fn slash(currency_id: Self::CurrencyId, who: &u64, amount: Self::Balance) -> Self::Balance {
+			let available = Self::balance_of(*who, &currency_id);
+			let slashed = available.min(amount);
+			let _ = Self::withdraw(currency_id, who, slashed);
+			amount - slashed
+		}
+	}
+
+	/// Resolves the test `MultiLocation::X1(Junction::AccountId32 { .. })` locations to the
+	/// first byte of the embedded 32-byte id, so deposit/withdraw tests can address distinct
+	/// accounts without needing a real relay-chain alias converter.
+	pub struct TestAccountIdConverter;
+	impl LocationConversion<u64> for TestAccountIdConverter {
+		fn from_location(location: &MultiLocation) -> Option<u64> {
+			if let MultiLocation::X1(Junction::AccountId32 { id, .. }) = location {
+				Some(id[0] as u64)
+			} else {
+				None
+			}
+		}
+		fn try_into_location(who: u64) -> result::Result<MultiLocation, u64> {
+			let mut id = [0u8; 32];
+			id[0] = who as u8;
+			Ok(MultiLocation::X1(Junction::AccountId32 { network: NetworkId::Any, id }))
+		}
+	}
+
+	thread_local! {
+		static ON_DEPOSIT_SHOULD_FAIL: RefCell<bool> = RefCell::new(false);
+		static ON_DEPOSIT_OBSERVED_BALANCE: RefCell<Option<u128>> = RefCell::new(None);
+	}
+
+	/// An `OnXcmDeposit` hook that records the balance it observes (so a test can assert the
+	/// credit already landed by the time the hook runs) and can be told to fail (so a test can
+	/// assert the credit is rolled back when it does).
+	pub struct MockOnDeposit;
+	impl MockOnDeposit {
+		pub fn set_should_fail(should_fail: bool) {
+			ON_DEPOSIT_SHOULD_FAIL.with(|f| *f.borrow_mut() = should_fail);
+		}
+
+		pub fn observed_balance() -> Option<u128> {
+			ON_DEPOSIT_OBSERVED_BALANCE.with(|b| *b.borrow())
+		}
+	}
+	impl OnXcmDeposit<u64, PHAXCurrencyId, u128> for MockOnDeposit {
+		fn on_deposit(who: &u64, currency_id: PHAXCurrencyId, _amount: u128) -> DispatchResult {
+			ON_DEPOSIT_OBSERVED_BALANCE.with(|b| *b.borrow_mut() = Some(FakeCurrency::balance_of(*who, &currency_id)));
+			if ON_DEPOSIT_SHOULD_FAIL.with(|f| *f.borrow()) {
+				Err(DispatchError::Other("on_deposit failed"))
+			} else {
+				Ok(())
+			}
+		}
+	}
+
+	thread_local! {
+		static NFT_OWNERS: RefCell<Vec<(Vec<u8>, u128, u64)>> = RefCell::new(Vec::new());
+	}
+
+	/// A uniques-like backend with real per-instance ownership, so a test can assert that
+	/// `burn_from` actually rejects a withdraw from an account that doesn't own the instance,
+	/// instead of the no-op `Ok(())` the backend contract only documented.
+	pub struct FakeNfts;
+	impl FakeNfts {
+		pub fn owner_of(class: &[u8], instance: u128) -> Option<u64> {
+			NFT_OWNERS.with(|o| {
+				o.borrow().iter()
+					.find(|(c, i, _)| c == class && *i == instance)
+					.map(|(_, _, who)| *who)
+			})
+		}
+	}
+	impl Nfts<u64> for FakeNfts {
+		fn mint_into(class: &[u8], instance: u128, who: &u64) -> DispatchResult {
+			NFT_OWNERS.with(|o| o.borrow_mut().push((class.to_vec(), instance, *who)));
+			Ok(())
+		}
+		fn burn_from(class: &[u8], instance: u128, who: &u64) -> DispatchResult {
+			NFT_OWNERS.with(|o| {
+				let mut owners = o.borrow_mut();
+				let pos = owners.iter()
+					.position(|(c, i, w)| c == class && *i == instance && w == who)
+					.ok_or(DispatchError::Other("who does not own this instance"))?;
+				owners.remove(pos);
+				Ok(())
+			})
+		}
+	}
+
+	impl Trait for Runtime {
+		type Event = ();
+		type Balance = u128;
+		type Matcher = IsConcreteWithGeneralKey<Runtime>;
+		type AccountIdConverter = TestAccountIdConverter;
+		type XCurrencyIdConverter = XCurrencyIdConverter<Runtime>;
+		type Currency = FakeCurrency;
+		type UnknownAsset = Module<Runtime>;
+		type OnDeposit = MockOnDeposit;
+		type NonFungibleMatcher = IsNonFungibleWithGeneralKey;
+		type Nfts = FakeNfts;
+		type RegisterOrigin = frame_system::EnsureRoot<u64>;
+	}
+
+	pub fn new_test_ext() -> sp_io::TestExternalities {
+		frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap().into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::mock::{self, new_test_ext, Origin, Runtime};
+
+	fn location(key: u8) -> MultiLocation {
+		MultiLocation::X1(Junction::GeneralKey(vec![key]))
+	}
+
+	fn currency(key: u8) -> PHAXCurrencyId {
+		PHAXCurrencyId::new(ChainId::ParaChain((key as u32).into()), vec![key])
+	}
+
+	fn account(who: u8) -> MultiLocation {
+		let mut id = [0u8; 32];
+		id[0] = who;
+		MultiLocation::X1(Junction::AccountId32 { network: NetworkId::Any, id })
+	}
+
+	fn concrete_asset(key: u8, amount: u128) -> MultiAsset {
+		MultiAsset::ConcreteFungible { id: location(key), amount }
+	}
+
+	fn nft_asset(class: u8, instance: u128) -> MultiAsset {
+		MultiAsset::AbstractNonFungible { class: vec![class], instance: AssetInstance::Index { id: instance } }
+	}
+
+	#[test]
+	fn register_asset_rejects_a_duplicate_key() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), b"A".to_vec(), currency(1), location(1), (1, 1)).is_ok());
+			assert_eq!(
+				Module::<Runtime>::register_asset(Origin::root(), b"A".to_vec(), currency(2), location(2), (1, 1)),
+				Err(Error::<Runtime>::AssetAlreadyRegistered.into()),
+			);
+		});
+	}
+
+	#[test]
+	fn register_asset_rejects_a_location_already_in_use() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), b"A".to_vec(), currency(1), location(1), (1, 1)).is_ok());
+			assert_eq!(
+				Module::<Runtime>::register_asset(Origin::root(), b"B".to_vec(), currency(2), location(1), (1, 1)),
+				Err(Error::<Runtime>::LocationAlreadyRegistered.into()),
+			);
+		});
+	}
+
+	#[test]
+	fn update_asset_location_rejects_a_location_already_in_use() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), b"A".to_vec(), currency(1), location(1), (1, 1)).is_ok());
+			assert!(Module::<Runtime>::register_asset(Origin::root(), b"B".to_vec(), currency(2), location(2), (1, 1)).is_ok());
+			assert_eq!(
+				Module::<Runtime>::update_asset_location(Origin::root(), b"A".to_vec(), location(2)),
+				Err(Error::<Runtime>::LocationAlreadyRegistered.into()),
+			);
+		});
+	}
+
+	#[test]
+	fn update_asset_location_preserves_the_ratio() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), b"A".to_vec(), currency(1), location(1), (3, 7)).is_ok());
+			assert!(Module::<Runtime>::update_asset_location(Origin::root(), b"A".to_vec(), location(2)).is_ok());
+
+			assert_eq!(AssetToLocation::get(b"A".to_vec()), Some(location(2)));
+			assert_eq!(AssetRatio::get(&location(2)), (3, 7));
+			assert!(!LocationToAsset::contains_key(&location(1)));
+		});
+	}
+
+	#[test]
+	fn deregister_asset_rejects_an_unknown_key() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				Module::<Runtime>::deregister_asset(Origin::root(), b"A".to_vec()),
+				Err(Error::<Runtime>::AssetNotRegistered.into()),
+			);
+		});
+	}
+
+	#[test]
+	fn deposit_asset_credits_the_real_balance() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![1], currency(1), location(1), (1, 1)).is_ok());
+
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(1)), 0);
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(1, 100), &account(1)).is_ok());
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(1)), 100);
+		});
+	}
+
+	#[test]
+	fn withdraw_asset_debits_the_real_balance() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![1], currency(1), location(1), (1, 1)).is_ok());
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(1, 100), &account(1)).is_ok());
+
+			assert!(Module::<Runtime>::withdraw_asset(&concrete_asset(1, 40), &account(1)).is_ok());
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(1)), 60);
+		});
+	}
+
+	#[test]
+	fn deposit_asset_rescales_the_amount_by_the_registered_ratio() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![2], currency(2), location(2), (1, 10)).is_ok());
+
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(2, 1000), &account(1)).is_ok());
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(2)), 100);
+		});
+	}
+
+	#[test]
+	fn withdraw_asset_rescales_the_amount_by_the_registered_ratio() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![2], currency(2), location(2), (1, 10)).is_ok());
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(2, 1000), &account(1)).is_ok());
+
+			assert!(Module::<Runtime>::withdraw_asset(&concrete_asset(2, 500), &account(1)).is_ok());
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(2)), 50);
+		});
+	}
+
+	#[test]
+	fn on_deposit_observes_the_credited_balance() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![1], currency(1), location(1), (1, 1)).is_ok());
+
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(1, 100), &account(1)).is_ok());
+			assert_eq!(mock::MockOnDeposit::observed_balance(), Some(100));
+		});
+	}
+
+	#[test]
+	fn deposit_asset_rolls_back_the_credit_when_on_deposit_fails() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![1], currency(1), location(1), (1, 1)).is_ok());
+			mock::MockOnDeposit::set_should_fail(true);
+
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(1, 100), &account(1)).is_err());
+			assert_eq!(mock::FakeCurrency::balance_of(1, &currency(1)), 0);
+		});
+	}
+
+	#[test]
+	fn deposit_asset_mints_a_non_fungible_to_its_owner() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(mock::FakeNfts::owner_of(&[7], 1), None);
+			assert!(Module::<Runtime>::deposit_asset(&nft_asset(7, 1), &account(1)).is_ok());
+			assert_eq!(mock::FakeNfts::owner_of(&[7], 1), Some(1));
+		});
+	}
+
+	#[test]
+	fn withdraw_asset_rejects_a_non_owner_burning_a_non_fungible() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::deposit_asset(&nft_asset(7, 1), &account(1)).is_ok());
+
+			assert!(Module::<Runtime>::withdraw_asset(&nft_asset(7, 1), &account(2)).is_err());
+			assert_eq!(mock::FakeNfts::owner_of(&[7], 1), Some(1));
+		});
+	}
+
+	#[test]
+	fn withdraw_asset_burns_a_non_fungible_owned_by_the_caller() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::deposit_asset(&nft_asset(7, 1), &account(1)).is_ok());
+
+			assert!(Module::<Runtime>::withdraw_asset(&nft_asset(7, 1), &account(1)).is_ok());
+			assert_eq!(mock::FakeNfts::owner_of(&[7], 1), None);
+		});
+	}
+
+	#[test]
+	fn deposit_asset_parks_an_unmapped_currency_in_the_unknown_asset_store() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(ConcreteFungibleBalances::get((account(1), location(9))), 0);
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(9, 50), &account(1)).is_ok());
+			assert_eq!(ConcreteFungibleBalances::get((account(1), location(9))), 50);
+		});
+	}
+
+	#[test]
+	fn withdraw_asset_rejects_overdrawing_the_unknown_asset_store() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::deposit_asset(&concrete_asset(9, 50), &account(1)).is_ok());
+
+			assert!(Module::<Runtime>::withdraw_asset(&concrete_asset(9, 51), &account(1)).is_err());
+			assert_eq!(ConcreteFungibleBalances::get((account(1), location(9))), 50);
+
+			assert!(Module::<Runtime>::withdraw_asset(&concrete_asset(9, 50), &account(1)).is_ok());
+			assert_eq!(ConcreteFungibleBalances::get((account(1), location(9))), 0);
+		});
+	}
+
+	#[test]
+	fn native_pallet_asset_or_accepts_the_registered_origin() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![6], currency(6), location(6), (1, 1)).is_ok());
+
+			assert!(NativePalletAssetOr::<Runtime>::filter_asset_location(&concrete_asset(6, 1), &location(6)));
+		});
+	}
+
+	#[test]
+	fn native_pallet_asset_or_rejects_a_mismatched_origin() {
+		new_test_ext().execute_with(|| {
+			assert!(Module::<Runtime>::register_asset(Origin::root(), vec![6], currency(6), location(6), (1, 1)).is_ok());
+
+			assert!(!NativePalletAssetOr::<Runtime>::filter_asset_location(&concrete_asset(6, 1), &location(7)));
+		});
+	}
 }
\ No newline at end of file